@@ -0,0 +1,100 @@
+//! Minimal MSB-first bit-level reader/writer used by the Gorilla page codec.
+
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    cur: u8,
+    nbits: u8, // number of valid bits already packed into `cur`, 0..8
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            cur: 0,
+            nbits: 0,
+        }
+    }
+
+    pub(crate) fn write_bit(&mut self, bit: bool) {
+        self.cur = (self.cur << 1) | (bit as u8);
+        self.nbits += 1;
+        if self.nbits == 8 {
+            self.bytes.push(self.cur);
+            self.cur = 0;
+            self.nbits = 0;
+        }
+    }
+
+    /// Writes the low `nbits` bits of `value`, most-significant bit first.
+    pub(crate) fn write_bits(&mut self, value: u64, nbits: u32) {
+        for i in (0..nbits).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    /// Writes the low `nbits` bits of a two's-complement signed value.
+    pub(crate) fn write_signed(&mut self, value: i64, nbits: u32) {
+        let mask = if nbits == 64 { u64::MAX } else { (1u64 << nbits) - 1 };
+        self.write_bits((value as u64) & mask, nbits);
+    }
+
+    /// Flushes any partial byte (zero-padded) and returns the packed bytes.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.cur <<= 8 - self.nbits;
+            self.bytes.push(self.cur);
+        }
+        self.bytes
+    }
+}
+
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    byte_pos: usize,
+    bit_pos: u8, // next bit to read within bytes[byte_pos], 0 = MSB
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        BitReader {
+            bytes,
+            byte_pos: 0,
+            bit_pos: 0,
+        }
+    }
+
+    pub(crate) fn read_bit(&mut self) -> bool {
+        let byte = self.bytes.get(self.byte_pos).copied().unwrap_or(0);
+        let bit = (byte >> (7 - self.bit_pos)) & 1 == 1;
+        self.bit_pos += 1;
+        if self.bit_pos == 8 {
+            self.bit_pos = 0;
+            self.byte_pos += 1;
+        }
+        bit
+    }
+
+    pub(crate) fn read_bits(&mut self, nbits: u32) -> u64 {
+        let mut value = 0u64;
+        for _ in 0..nbits {
+            value = (value << 1) | (self.read_bit() as u64);
+        }
+        value
+    }
+
+    /// Reads `nbits` bits and sign-extends them to an `i64`.
+    pub(crate) fn read_signed(&mut self, nbits: u32) -> i64 {
+        let raw = self.read_bits(nbits);
+        if nbits == 64 {
+            // `raw` is already the exact two's-complement bit pattern; no bias to subtract,
+            // and `1i64 << 64` would overflow.
+            return raw as i64;
+        }
+        let sign_bit = 1u64 << (nbits - 1);
+        if raw & sign_bit != 0 {
+            (raw as i64) - (1i64 << nbits)
+        } else {
+            raw as i64
+        }
+    }
+}
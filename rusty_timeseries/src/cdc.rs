@@ -0,0 +1,86 @@
+//! Content-defined chunking for deduplicated backup export (see `backup`).
+//!
+//! A buzhash rolling hash slides a [`WINDOW_SIZE`]-byte window over the
+//! input and cuts a chunk boundary whenever its low bits are all zero,
+//! producing variable-length chunks that average [`AVG_CHUNK_SIZE`] bytes.
+//! Because cut points are decided from local content rather than a fixed
+//! byte offset, appending rows to a table only changes the chunk(s) near
+//! the end of the stream -- unlike fixed-size blocking, where an append
+//! that isn't a multiple of the block size shifts every block after it
+//! and defeats dedup entirely.
+
+const WINDOW_SIZE: usize = 64;
+const AVG_CHUNK_SIZE: usize = 64 * 1024;
+const BOUNDARY_MASK: u64 = (AVG_CHUNK_SIZE as u64) - 1;
+const MIN_CHUNK_SIZE: usize = AVG_CHUNK_SIZE / 4;
+const MAX_CHUNK_SIZE: usize = AVG_CHUNK_SIZE * 4;
+
+/// A fixed table mapping each byte value to a pseudo-random 64-bit mixer,
+/// generated once at compile time from a fixed seed (buzhash doesn't need
+/// cryptographic randomness, just even bit distribution).
+const fn buzhash_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut z = (i as u64).wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+const TABLE: [u64; 256] = buzhash_table();
+
+/// Splits `data` into content-defined chunks. Every chunk but the last is
+/// at least [`MIN_CHUNK_SIZE`] bytes and at most [`MAX_CHUNK_SIZE`] bytes;
+/// empty input produces no chunks.
+pub(crate) fn split_chunks(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    if data.is_empty() {
+        return chunks;
+    }
+
+    let mut start = 0usize;
+    let mut hash: u64 = 0;
+    let mut window = [0u8; WINDOW_SIZE];
+    let mut window_len = 0usize;
+    let mut window_pos = 0usize;
+
+    for (i, &byte_in) in data.iter().enumerate() {
+        if window_len < WINDOW_SIZE {
+            hash = hash.rotate_left(1) ^ TABLE[byte_in as usize];
+            window[window_pos] = byte_in;
+            window_pos = (window_pos + 1) % WINDOW_SIZE;
+            window_len += 1;
+        } else {
+            let byte_out = window[window_pos];
+            hash = hash.rotate_left(1)
+                ^ TABLE[byte_out as usize].rotate_left(WINDOW_SIZE as u32)
+                ^ TABLE[byte_in as usize];
+            window[window_pos] = byte_in;
+            window_pos = (window_pos + 1) % WINDOW_SIZE;
+        }
+
+        let chunk_len = i + 1 - start;
+        let at_content_boundary =
+            window_len == WINDOW_SIZE && hash & BOUNDARY_MASK == 0 && chunk_len >= MIN_CHUNK_SIZE;
+        let at_forced_boundary = chunk_len >= MAX_CHUNK_SIZE;
+
+        if at_content_boundary || at_forced_boundary {
+            chunks.push(&data[start..=i]);
+            start = i + 1;
+            hash = 0;
+            window_len = 0;
+            window_pos = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(&data[start..]);
+    }
+
+    chunks
+}
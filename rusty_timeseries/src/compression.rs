@@ -0,0 +1,63 @@
+//! Pluggable block compression for closed (already-rolled) segments.
+//!
+//! The active segment a table is appending to is always stored raw so
+//! inserts can append row-at-a-time and `fsync` cheaply; compression only
+//! ever applies when a segment is rewritten wholesale, which today means
+//! [`crate::store::SegmentStore::compact`]. A segment's codec is recorded
+//! in its own header byte (see `store`), so segments written under
+//! different codecs can be mixed in the same table and each is
+//! decompressed correctly on read.
+
+use crate::{deserialize_row, serialize_row, TimeseriesData, ROW_SIZE};
+use crate::gorilla;
+use lz4_flex::block::{compress_prepend_size, decompress_size_prepended};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompressionType {
+    None = 0,
+    Lz4 = 1,
+    Gorilla = 2,
+}
+
+impl CompressionType {
+    pub(crate) fn from_u8(byte: u8) -> Self {
+        match byte {
+            1 => CompressionType::Lz4,
+            2 => CompressionType::Gorilla,
+            _ => CompressionType::None,
+        }
+    }
+}
+
+pub(crate) fn compress(codec: CompressionType, data: &[u8]) -> Vec<u8> {
+    match codec {
+        CompressionType::None => data.to_vec(),
+        CompressionType::Lz4 => compress_prepend_size(data),
+        CompressionType::Gorilla => gorilla::compress_page(&rows_from_bytes(data)),
+    }
+}
+
+pub(crate) fn decompress(codec: CompressionType, data: &[u8]) -> Vec<u8> {
+    match codec {
+        CompressionType::None => data.to_vec(),
+        CompressionType::Lz4 => {
+            decompress_size_prepended(data).expect("Corrupt LZ4-compressed segment block")
+        }
+        CompressionType::Gorilla => rows_to_bytes(&gorilla::decompress_page(data)),
+    }
+}
+
+fn rows_from_bytes(data: &[u8]) -> Vec<TimeseriesData> {
+    let row_count = data.len() / ROW_SIZE;
+    (0..row_count)
+        .map(|i| deserialize_row(&data[i * ROW_SIZE..(i + 1) * ROW_SIZE]))
+        .collect()
+}
+
+fn rows_to_bytes(rows: &[TimeseriesData]) -> Vec<u8> {
+    let mut out = vec![0u8; rows.len() * ROW_SIZE];
+    for (i, row) in rows.iter().enumerate() {
+        serialize_row(row, &mut out[i * ROW_SIZE..(i + 1) * ROW_SIZE]);
+    }
+    out
+}
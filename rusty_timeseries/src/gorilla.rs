@@ -0,0 +1,317 @@
+//! Gorilla-style compression for a page's timestamps and values.
+//!
+//! Timestamps are delta-of-delta encoded and values are XORed against the
+//! previous value, following the scheme from Facebook's Gorilla paper. The
+//! remaining per-row fields (sensor name, fault flag, timeseries id) don't
+//! compress the same way, but they're also mostly constant for runs of
+//! consecutive rows belonging to the same timeseries, so they're
+//! run-length encoded instead of repeated verbatim per row: a page holding
+//! a single timeseries (the common case once `compact` has rewritten a
+//! segment) pays for that metadata exactly once.
+
+use crate::bitio::{BitReader, BitWriter};
+use crate::{TimeseriesData, SENSOR_NAME_SIZE, TIMESERIES_ID_SIZE};
+use chrono::DateTime;
+use uuid::Uuid;
+
+fn compress_timestamps(timestamps: &[i64]) -> Vec<u8> {
+    let mut bw = BitWriter::new();
+    if timestamps.is_empty() {
+        return bw.finish();
+    }
+    bw.write_bits(timestamps[0] as u64, 64);
+    if timestamps.len() == 1 {
+        return bw.finish();
+    }
+
+    let mut prev_delta = timestamps[1] - timestamps[0];
+    bw.write_signed(prev_delta, 64);
+    let mut prev_ts = timestamps[1];
+
+    for &ts in &timestamps[2..] {
+        let delta = ts - prev_ts;
+        let dod = delta - prev_delta;
+        encode_dod(&mut bw, dod);
+        prev_delta = delta;
+        prev_ts = ts;
+    }
+
+    bw.finish()
+}
+
+// Each bucket below spans exactly `2^nbits` values (e.g. -63..=64 is 128
+// values), one more than an n-bit two's-complement range can hold. So a
+// bucket's value is bias-encoded as `d - LOWER_BOUND` in `nbits` unsigned
+// bits rather than stored two's-complement, or the top of every bucket
+// (e.g. a dod of exactly 64) would silently wrap to its negation on decode.
+fn encode_dod(bw: &mut BitWriter, d: i64) {
+    if d == 0 {
+        bw.write_bit(false);
+    } else if (-63..=64).contains(&d) {
+        bw.write_bits(0b10, 2);
+        bw.write_bits((d - (-63)) as u64, 7);
+    } else if (-255..=256).contains(&d) {
+        bw.write_bits(0b110, 3);
+        bw.write_bits((d - (-255)) as u64, 9);
+    } else if (-2047..=2048).contains(&d) {
+        bw.write_bits(0b1110, 4);
+        bw.write_bits((d - (-2047)) as u64, 12);
+    } else if (-2_147_483_647..=2_147_483_648).contains(&d) {
+        bw.write_bits(0b11110, 5);
+        bw.write_bits((d - (-2_147_483_647)) as u64, 32);
+    } else {
+        // Nanosecond-resolution deltas can blow past the 32-bit bucket the
+        // classic Gorilla scheme sizes for second-granularity data; fall
+        // back to a raw 64-bit delta-of-delta instead of truncating it.
+        // This bucket spans the full i64 range, so two's-complement (not
+        // bias encoding) is exact here.
+        bw.write_bits(0b11111, 5);
+        bw.write_signed(d, 64);
+    }
+}
+
+fn decode_dod(br: &mut BitReader) -> i64 {
+    if !br.read_bit() {
+        return 0;
+    }
+    if !br.read_bit() {
+        return br.read_bits(7) as i64 + (-63);
+    }
+    if !br.read_bit() {
+        return br.read_bits(9) as i64 + (-255);
+    }
+    if !br.read_bit() {
+        return br.read_bits(12) as i64 + (-2047);
+    }
+    if !br.read_bit() {
+        return br.read_bits(32) as i64 + (-2_147_483_647);
+    }
+    br.read_signed(64)
+}
+
+fn decompress_timestamps(bytes: &[u8], count: usize) -> Vec<i64> {
+    let mut out = Vec::with_capacity(count);
+    if count == 0 {
+        return out;
+    }
+    let mut br = BitReader::new(bytes);
+    let t0 = br.read_bits(64) as i64;
+    out.push(t0);
+    if count == 1 {
+        return out;
+    }
+
+    let mut prev_delta = br.read_signed(64);
+    let mut prev_ts = t0 + prev_delta;
+    out.push(prev_ts);
+
+    for _ in 2..count {
+        let dod = decode_dod(&mut br);
+        let delta = prev_delta + dod;
+        let ts = prev_ts + delta;
+        out.push(ts);
+        prev_delta = delta;
+        prev_ts = ts;
+    }
+
+    out
+}
+
+fn compress_values(values: &[f64]) -> Vec<u8> {
+    let mut bw = BitWriter::new();
+    if values.is_empty() {
+        return bw.finish();
+    }
+
+    let mut prev = values[0].to_bits();
+    bw.write_bits(prev, 64);
+
+    // No previous meaningful-bits window yet.
+    let mut window: Option<(u32, u32)> = None;
+
+    for &value in &values[1..] {
+        let cur = value.to_bits();
+        let xor = cur ^ prev;
+        if xor == 0 {
+            bw.write_bit(false);
+        } else {
+            bw.write_bit(true);
+            let leading = xor.leading_zeros().min(31);
+            let trailing = xor.trailing_zeros();
+
+            let reuse_window = matches!(window, Some((w_leading, w_trailing)) if leading >= w_leading && trailing >= w_trailing);
+
+            if reuse_window {
+                let (w_leading, w_trailing) = window.unwrap();
+                bw.write_bit(false);
+                let meaningful_len = 64 - w_leading - w_trailing;
+                bw.write_bits(xor >> w_trailing, meaningful_len);
+            } else {
+                bw.write_bit(true);
+                let meaningful_len = 64 - leading - trailing;
+                bw.write_bits(leading as u64, 5);
+                bw.write_bits(meaningful_len as u64, 6);
+                bw.write_bits(xor >> trailing, meaningful_len);
+                window = Some((leading, trailing));
+            }
+        }
+        prev = cur;
+    }
+
+    bw.finish()
+}
+
+fn decompress_values(bytes: &[u8], count: usize) -> Vec<f64> {
+    let mut out = Vec::with_capacity(count);
+    if count == 0 {
+        return out;
+    }
+    let mut br = BitReader::new(bytes);
+    let mut prev = br.read_bits(64);
+    out.push(f64::from_bits(prev));
+
+    let mut window: (u32, u32) = (64, 0); // sentinel: no window established
+
+    for _ in 1..count {
+        if !br.read_bit() {
+            out.push(f64::from_bits(prev));
+            continue;
+        }
+        if !br.read_bit() {
+            let (w_leading, w_trailing) = window;
+            let meaningful_len = 64 - w_leading - w_trailing;
+            let bits = br.read_bits(meaningful_len) << w_trailing;
+            prev ^= bits;
+        } else {
+            let leading = br.read_bits(5) as u32;
+            let meaningful_len = br.read_bits(6) as u32;
+            let trailing = 64 - leading - meaningful_len;
+            let bits = br.read_bits(meaningful_len) << trailing;
+            prev ^= bits;
+            window = (leading, trailing);
+        }
+        out.push(f64::from_bits(prev));
+    }
+
+    out
+}
+
+/// Run-length encodes the identity fields (sensor name, fault flag,
+/// timeseries id) that `compress_timestamps`/`compress_values` don't cover.
+/// These tend to stay constant across a run of consecutive rows -- a page
+/// holding a single timeseries collapses to one run -- so paying for them
+/// once per run rather than once per row is where most of a page's
+/// metadata savings come from.
+fn compress_meta(rows: &[TimeseriesData]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < rows.len() {
+        let row = &rows[i];
+        let mut run_len = 1u32;
+        while i + (run_len as usize) < rows.len() {
+            let next = &rows[i + run_len as usize];
+            if next.sensor_name != row.sensor_name
+                || next.fc1_flag != row.fc1_flag
+                || next.timeseries_id != row.timeseries_id
+            {
+                break;
+            }
+            run_len += 1;
+        }
+
+        out.extend_from_slice(&run_len.to_le_bytes());
+        let mut name_buf = [0u8; SENSOR_NAME_SIZE];
+        let name_bytes = row.sensor_name.as_bytes();
+        let n = name_bytes.len().min(SENSOR_NAME_SIZE);
+        name_buf[..n].copy_from_slice(&name_bytes[..n]);
+        out.extend_from_slice(&name_buf);
+        out.push(row.fc1_flag.unwrap_or(0));
+        out.extend_from_slice(row.timeseries_id.as_bytes());
+
+        i += run_len as usize;
+    }
+    out
+}
+
+fn decompress_meta(bytes: &[u8], row_count: usize) -> Vec<(String, Option<u8>, Uuid)> {
+    let mut out = Vec::with_capacity(row_count);
+    let mut offset = 0;
+    while out.len() < row_count {
+        let run_len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+
+        let name_start = offset;
+        let flag_offset = name_start + SENSOR_NAME_SIZE;
+        let id_start = flag_offset + 1;
+        let sensor_name = String::from_utf8(bytes[name_start..flag_offset].to_vec())
+            .unwrap()
+            .trim_end_matches(char::from(0))
+            .to_string();
+        let flag_byte = bytes[flag_offset];
+        let fc1_flag = if flag_byte != 0 { Some(flag_byte) } else { None };
+        let timeseries_id =
+            Uuid::from_bytes(bytes[id_start..id_start + TIMESERIES_ID_SIZE].try_into().unwrap());
+        offset = id_start + TIMESERIES_ID_SIZE;
+
+        for _ in 0..run_len {
+            out.push((sensor_name.clone(), fc1_flag, timeseries_id));
+        }
+    }
+    out
+}
+
+/// Encodes a page's rows into a self-describing compressed block: a row
+/// count, the Gorilla-compressed timestamp and value streams, and the
+/// run-length encoded identity fields.
+pub(crate) fn compress_page(rows: &[TimeseriesData]) -> Vec<u8> {
+    let timestamps: Vec<i64> = rows
+        .iter()
+        .map(|r| r.timestamp.timestamp_nanos_opt().unwrap())
+        .collect();
+    let values: Vec<f64> = rows.iter().map(|r| r.value).collect();
+
+    let ts_block = compress_timestamps(&timestamps);
+    let val_block = compress_values(&values);
+    let meta_block = compress_meta(rows);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(ts_block.len() as u32).to_le_bytes());
+    out.extend_from_slice(&ts_block);
+    out.extend_from_slice(&(val_block.len() as u32).to_le_bytes());
+    out.extend_from_slice(&val_block);
+    out.extend_from_slice(&meta_block);
+
+    out
+}
+
+pub(crate) fn decompress_page(block: &[u8]) -> Vec<TimeseriesData> {
+    let row_count = u32::from_le_bytes(block[0..4].try_into().unwrap()) as usize;
+    let mut offset = 4;
+
+    let ts_len = u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap()) as usize;
+    offset += 4;
+    let timestamps = decompress_timestamps(&block[offset..offset + ts_len], row_count);
+    offset += ts_len;
+
+    let val_len = u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap()) as usize;
+    offset += 4;
+    let values = decompress_values(&block[offset..offset + val_len], row_count);
+    offset += val_len;
+
+    let meta = decompress_meta(&block[offset..], row_count);
+
+    let mut rows = Vec::with_capacity(row_count);
+    for i in 0..row_count {
+        let (sensor_name, fc1_flag, timeseries_id) = meta[i].clone();
+        rows.push(TimeseriesData {
+            sensor_name,
+            timestamp: DateTime::from_timestamp_nanos(timestamps[i]),
+            value: values[i],
+            fc1_flag,
+            timeseries_id,
+        });
+    }
+
+    rows
+}
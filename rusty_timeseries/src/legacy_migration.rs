@@ -0,0 +1,193 @@
+//! One-time readers for on-disk formats that predate the append-only
+//! segment store (see `store`). A pre-existing `brick_timeseries.db` is
+//! always a single monolithic file in one of these formats; `Table::new`
+//! detects that and calls [`read_old_database`] to pull every row out of
+//! it before replaying them into a fresh `SegmentStore`.
+
+use crate::gorilla;
+use crate::{deserialize_row, TimeseriesData, FLAG_SIZE, ROW_SIZE, SENSOR_NAME_SIZE, VALUE_SIZE};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+const PAGE_SIZE: usize = 4096;
+
+const FORMAT_MAGIC: &[u8; 4] = b"RTSD";
+const FORMAT_HEADER_SIZE_V2: usize = FORMAT_MAGIC.len() + 1;
+const FORMAT_HEADER_SIZE_V3: usize = FORMAT_MAGIC.len() + 1 + 1;
+
+const ROWS_PER_PAGE: usize = PAGE_SIZE / ROW_SIZE;
+
+// Legacy (version-1, unversioned) row layout: both timestamp and
+// timeseries_id were zero-padded strings.
+const LEGACY_TIMESTAMP_SIZE: usize = 32;
+const LEGACY_TIMESERIES_ID_SIZE: usize = 32;
+const LEGACY_ROW_SIZE: usize =
+    SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE + VALUE_SIZE + FLAG_SIZE + LEGACY_TIMESERIES_ID_SIZE;
+const LEGACY_ROWS_PER_PAGE: usize = PAGE_SIZE / LEGACY_ROW_SIZE;
+
+/// The page codec a pre-segment-store v3 file was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageCodec {
+    Raw = 0,
+    Gorilla = 1,
+}
+
+impl PageCodec {
+    fn from_u8(byte: u8) -> Self {
+        match byte {
+            1 => PageCodec::Gorilla,
+            _ => PageCodec::Raw,
+        }
+    }
+}
+
+/// Reads every row out of a pre-segment-store `brick_timeseries.db` file,
+/// whatever vintage it happens to be (unversioned string rows, v2 raw
+/// pages, or v3 codec-framed pages). Rows are returned in on-disk order so
+/// callers can replay them into a `SegmentStore` and preserve history.
+pub(crate) fn read_old_database(path: &Path) -> Vec<TimeseriesData> {
+    let raw = fs::read(path).expect("Unable to read legacy database file");
+    if raw.is_empty() {
+        return Vec::new();
+    }
+
+    if raw.len() >= FORMAT_HEADER_SIZE_V2 && raw[..FORMAT_MAGIC.len()] == *FORMAT_MAGIC {
+        match raw[FORMAT_MAGIC.len()] {
+            2 => read_v2(&raw[FORMAT_HEADER_SIZE_V2..]),
+            3 => {
+                let codec = PageCodec::from_u8(raw[FORMAT_MAGIC.len() + 1]);
+                read_v3(&raw[FORMAT_HEADER_SIZE_V3..], codec)
+            }
+            other => panic!("Unsupported legacy database format version {}", other),
+        }
+    } else {
+        read_legacy(&raw)
+    }
+}
+
+fn read_v2(body: &[u8]) -> Vec<TimeseriesData> {
+    let num_rows = body.len() / ROW_SIZE;
+    let mut rows = Vec::with_capacity(num_rows);
+    for i in 0..num_rows {
+        let page_num = i / ROWS_PER_PAGE;
+        let row_in_page = i % ROWS_PER_PAGE;
+        let start = page_num * PAGE_SIZE + row_in_page * ROW_SIZE;
+        if start + ROW_SIZE > body.len() {
+            break;
+        }
+        rows.push(deserialize_row(&body[start..start + ROW_SIZE]));
+    }
+    rows
+}
+
+fn read_v3(body: &[u8], codec: PageCodec) -> Vec<TimeseriesData> {
+    let mut rows = Vec::new();
+    let mut offset = 0;
+
+    while offset + 8 <= body.len() {
+        let row_count = u32::from_le_bytes(body[offset..offset + 4].try_into().unwrap()) as usize;
+        let block_len = u32::from_le_bytes(body[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        offset += 8;
+        if offset + block_len > body.len() {
+            break;
+        }
+        let block = &body[offset..offset + block_len];
+        offset += block_len;
+
+        match codec {
+            PageCodec::Raw => {
+                for i in 0..row_count {
+                    let start = i * ROW_SIZE;
+                    if start + ROW_SIZE > block.len() {
+                        break;
+                    }
+                    rows.push(deserialize_row(&block[start..start + ROW_SIZE]));
+                }
+            }
+            PageCodec::Gorilla => rows.extend(gorilla::decompress_page(block)),
+        }
+    }
+
+    rows
+}
+
+fn read_legacy(raw: &[u8]) -> Vec<TimeseriesData> {
+    let legacy_row_count = raw.len() / LEGACY_ROW_SIZE;
+    let mut rows = Vec::with_capacity(legacy_row_count);
+    for i in 0..legacy_row_count {
+        let page = i / LEGACY_ROWS_PER_PAGE;
+        let row_in_page = i % LEGACY_ROWS_PER_PAGE;
+        let start = page * PAGE_SIZE + row_in_page * LEGACY_ROW_SIZE;
+        if start + LEGACY_ROW_SIZE > raw.len() {
+            break;
+        }
+        match deserialize_legacy_row(&raw[start..start + LEGACY_ROW_SIZE]) {
+            Ok(data) => rows.push(data),
+            Err(err) => eprintln!("Skipping unreadable legacy row {}: {}", i, err),
+        }
+    }
+    rows
+}
+
+/// Parses a row in the pre-migration format, where `timestamp` was an
+/// RFC3339 string and `timeseries_id` a hyphenated UUID string, both
+/// zero-padded to a fixed width.
+fn deserialize_legacy_row(source: &[u8]) -> Result<TimeseriesData, String> {
+    let sensor_name = String::from_utf8(source[..SENSOR_NAME_SIZE].to_vec())
+        .map_err(|e| e.to_string())?
+        .trim_end_matches(char::from(0))
+        .to_string();
+
+    let timestamp_str = String::from_utf8(
+        source[SENSOR_NAME_SIZE..SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE].to_vec(),
+    )
+    .map_err(|e| e.to_string())?
+    .trim_end_matches(char::from(0))
+    .to_string();
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|e| e.to_string())?
+        .with_timezone(&Utc);
+    if timestamp.timestamp_nanos_opt().is_none() {
+        return Err(format!(
+            "timestamp {} is out of the representable i64-nanosecond range",
+            timestamp_str
+        ));
+    }
+
+    let value = f64::from_ne_bytes(
+        source[SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE
+            ..SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE + VALUE_SIZE]
+            .try_into()
+            .map_err(|_| "value field truncated".to_string())?,
+    );
+    let fc1_flag_byte = source[SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE + VALUE_SIZE];
+    let fc1_flag = if fc1_flag_byte != 0 {
+        Some(fc1_flag_byte)
+    } else {
+        None
+    };
+
+    let timeseries_id_str = String::from_utf8(
+        source[SENSOR_NAME_SIZE + LEGACY_TIMESTAMP_SIZE + VALUE_SIZE + FLAG_SIZE
+            ..SENSOR_NAME_SIZE
+                + LEGACY_TIMESTAMP_SIZE
+                + VALUE_SIZE
+                + FLAG_SIZE
+                + LEGACY_TIMESERIES_ID_SIZE]
+            .to_vec(),
+    )
+    .map_err(|e| e.to_string())?
+    .trim_end_matches(char::from(0))
+    .to_string();
+    let timeseries_id = Uuid::parse_str(&timeseries_id_str).map_err(|e| e.to_string())?;
+
+    Ok(TimeseriesData {
+        sensor_name,
+        timestamp,
+        value,
+        fc1_flag,
+        timeseries_id,
+    })
+}
@@ -0,0 +1,66 @@
+//! A point-in-time snapshot of a table's rows, streamable to/from any
+//! `Write`/`Read` so it can be written to a file or sent over a socket
+//! (see `Table::snapshot`/`Table::restore` and the `/snapshot`,
+//! `/restore` HTTP routes).
+
+use crate::{deserialize_row, serialize_row, TimeseriesData, ROW_SIZE};
+use std::io::{self, Read, Write};
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"RTSSNAP1";
+const SNAPSHOT_VERSION: u8 = 1;
+
+pub(crate) struct Snapshot {
+    pub(crate) rows: Vec<TimeseriesData>,
+}
+
+impl Snapshot {
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&[SNAPSHOT_VERSION])?;
+        writer.write_all(&(self.rows.len() as u32).to_le_bytes())?;
+
+        let mut buf = vec![0u8; ROW_SIZE];
+        for row in &self.rows {
+            serialize_row(row, &mut buf);
+            writer.write_all(&buf)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a recognized snapshot stream",
+            ));
+        }
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported snapshot version {}", version[0]),
+            ));
+        }
+
+        let mut count_bytes = [0u8; 4];
+        reader.read_exact(&mut count_bytes)?;
+        let count = u32::from_le_bytes(count_bytes) as usize;
+
+        // `count` is attacker-controlled (it comes straight off the wire via
+        // `/restore`), so don't pre-reserve a `Vec` sized from it -- that's a
+        // trivial memory-exhaustion DoS. Grow the `Vec` one row at a time as
+        // rows are actually read instead.
+        let mut rows = Vec::new();
+        let mut buf = vec![0u8; ROW_SIZE];
+        for _ in 0..count {
+            reader.read_exact(&mut buf)?;
+            rows.push(deserialize_row(&buf));
+        }
+
+        Ok(Snapshot { rows })
+    }
+}
@@ -0,0 +1,569 @@
+//! Append-only segmented row log.
+//!
+//! Inserts append a row to the tail of the current segment file and
+//! `fsync`, rolling to a new segment once the active one reaches
+//! [`MAX_SEGMENT_BYTES`]. An in-memory index maps each `timeseries_id` to
+//! the segments (and the byte offset within each) that hold its rows, so
+//! range queries can skip segments whose time span doesn't overlap the
+//! query instead of scanning everything. Closed segments carry a trailer
+//! with their index entries so the index can be rebuilt on startup
+//! without a full scan; a missing or corrupt trailer falls back to a
+//! sequential scan of that segment.
+//!
+//! A closed segment's row data may additionally be compressed (see
+//! `compression`) -- the active segment never is, since inserts need to
+//! append to it in place. Compression is applied when [`compact`] rewrites
+//! a segment through the table's current codec; the `byte_offset` recorded
+//! in a [`SegmentIndexEntry`] always refers to a position in the
+//! *decompressed* row stream, so it means the same thing regardless of
+//! whether the segment it points at happens to be compressed.
+//!
+//! [`compact`]: SegmentStore::compact
+
+use crate::compression::{self, CompressionType};
+use crate::{deserialize_row, serialize_row, TimeseriesData, ROW_SIZE};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const MAX_SEGMENT_BYTES: u64 = 1024 * 1024;
+const TRAILER_MAGIC: &[u8; 8] = b"SEGTRLR1";
+const TRAILER_ENTRY_SIZE: usize = 16 + 8 + 8 + 8 + 4; // uuid + min_ts + max_ts + byte_offset + count
+
+// Header written in front of a segment's row data when it's compressed:
+// magic + codec byte + compressed length. A raw (uncompressed) segment has
+// no such header -- its row data starts at offset 0.
+const COMPRESSED_SEGMENT_MAGIC: &[u8; 8] = b"SEGCOMP1";
+const COMPRESSED_SEGMENT_HEADER_SIZE: usize = 8 + 1 + 8;
+
+const CODEC_FILE_NAME: &str = "CODEC";
+
+#[derive(Debug, Clone, Copy)]
+struct SegmentIndexEntry {
+    min_ts: i64,
+    max_ts: i64,
+    segment_id: u32,
+    byte_offset: u64,
+    count: u32,
+}
+
+pub(crate) struct SegmentStore {
+    dir: PathBuf,
+    active_id: u32,
+    active_file: File,
+    active_len: u64,
+    index: HashMap<Uuid, Vec<SegmentIndexEntry>>,
+    codec: CompressionType,
+}
+
+impl SegmentStore {
+    pub(crate) fn open(dir: &Path) -> Self {
+        fs::create_dir_all(dir).expect("Unable to create segment directory");
+
+        let mut ids = list_segment_ids(dir);
+        ids.sort_unstable();
+
+        let mut index: HashMap<Uuid, Vec<SegmentIndexEntry>> = HashMap::new();
+        let mut tail_is_closed = false;
+
+        for &id in &ids {
+            let path = segment_path(dir, id);
+            let len = fs::metadata(&path)
+                .expect("Unable to stat segment file")
+                .len();
+            match try_read_trailer(&path, len, id) {
+                Some(entries) => {
+                    tail_is_closed = true;
+                    merge_entries(&mut index, entries);
+                }
+                None => {
+                    tail_is_closed = false;
+                    merge_entries(&mut index, scan_segment(&path, id, len));
+                }
+            }
+        }
+
+        let (active_id, active_len) = if ids.is_empty() {
+            (0, 0)
+        } else {
+            let tail_id = *ids.last().unwrap();
+            if tail_is_closed {
+                (tail_id + 1, 0)
+            } else {
+                let len = fs::metadata(segment_path(dir, tail_id)).unwrap().len();
+                (tail_id, len)
+            }
+        };
+
+        let active_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(dir, active_id))
+            .expect("Unable to open active segment file");
+
+        let codec = fs::read(dir.join(CODEC_FILE_NAME))
+            .ok()
+            .and_then(|bytes| bytes.first().copied())
+            .map(CompressionType::from_u8)
+            .unwrap_or(CompressionType::None);
+
+        SegmentStore {
+            dir: dir.to_path_buf(),
+            active_id,
+            active_file,
+            active_len,
+            index,
+            codec,
+        }
+    }
+
+    /// Changes the codec used the next time [`compact`](Self::compact)
+    /// rewrites segments, and remembers the choice in a small header file
+    /// so it survives a restart.
+    pub(crate) fn set_compression(&mut self, codec: CompressionType) -> std::io::Result<()> {
+        fs::write(self.dir.join(CODEC_FILE_NAME), [codec as u8])?;
+        self.codec = codec;
+        Ok(())
+    }
+
+    /// Rewrites every closed segment through the table's current
+    /// compression codec, reclaiming space from rows that were updated or
+    /// from segments written under a different (or no) codec. The active
+    /// segment is left alone since it's still being appended to.
+    pub(crate) fn compact(&mut self) -> std::io::Result<()> {
+        let mut ids = list_segment_ids(&self.dir);
+        ids.sort_unstable();
+
+        for id in ids {
+            if id == self.active_id {
+                continue;
+            }
+            let path = segment_path(&self.dir, id);
+            let len = fs::metadata(&path)?.len();
+            let data_len = segment_data_len(&path, id);
+
+            let rows = read_segment_rows(&path, id);
+            let mut trailer = vec![0u8; (len - data_len) as usize];
+            let mut file = File::open(&path)?;
+            file.seek(SeekFrom::Start(data_len))?;
+            file.read_exact(&mut trailer)?;
+            drop(file);
+
+            write_segment_with_codec(&path, self.codec, &rows, &trailer)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn insert(&mut self, data: &TimeseriesData) -> std::io::Result<()> {
+        let ts = checked_nanos(data.timestamp).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })?;
+
+        let mut buf = vec![0u8; ROW_SIZE];
+        serialize_row(data, &mut buf);
+
+        let offset = self.active_len;
+        self.active_file.write_all(&buf)?;
+        self.active_file.sync_all()?;
+        self.active_len += ROW_SIZE as u64;
+
+        let entries = self.index.entry(data.timeseries_id).or_default();
+        match entries
+            .last_mut()
+            .filter(|e| e.segment_id == self.active_id)
+        {
+            Some(last) => {
+                last.min_ts = last.min_ts.min(ts);
+                last.max_ts = last.max_ts.max(ts);
+                last.count += 1;
+            }
+            None => entries.push(SegmentIndexEntry {
+                min_ts: ts,
+                max_ts: ts,
+                segment_id: self.active_id,
+                byte_offset: offset,
+                count: 1,
+            }),
+        }
+
+        if self.active_len >= MAX_SEGMENT_BYTES {
+            self.roll_segment()?;
+        }
+
+        Ok(())
+    }
+
+    fn roll_segment(&mut self) -> std::io::Result<()> {
+        let active_id = self.active_id;
+        let entries: Vec<(Uuid, SegmentIndexEntry)> = self
+            .index
+            .iter()
+            .flat_map(|(uuid, v)| {
+                v.iter()
+                    .filter(move |e| e.segment_id == active_id)
+                    .map(move |e| (*uuid, *e))
+            })
+            .collect();
+
+        for (uuid, entry) in &entries {
+            self.active_file.write_all(uuid.as_bytes())?;
+            self.active_file.write_all(&entry.min_ts.to_le_bytes())?;
+            self.active_file.write_all(&entry.max_ts.to_le_bytes())?;
+            self.active_file.write_all(&entry.byte_offset.to_le_bytes())?;
+            self.active_file.write_all(&entry.count.to_le_bytes())?;
+        }
+        self.active_file
+            .write_all(&(entries.len() as u32).to_le_bytes())?;
+        self.active_file.write_all(TRAILER_MAGIC)?;
+        self.active_file.sync_all()?;
+
+        self.active_id += 1;
+        self.active_len = 0;
+        self.active_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(&self.dir, self.active_id))?;
+
+        Ok(())
+    }
+
+    pub(crate) fn query(
+        &self,
+        timeseries_id: Uuid,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Vec<TimeseriesData> {
+        // A caller-supplied bound outside the representable range can't
+        // match any stored row in that direction anyway (every stored
+        // timestamp passed `checked_nanos` on insert), so saturate to the
+        // representable extreme on the same side of the epoch instead of
+        // panicking on it.
+        let start_nanos = saturating_nanos(start_time);
+        let end_nanos = saturating_nanos(end_time);
+
+        let mut results = Vec::new();
+        let Some(entries) = self.index.get(&timeseries_id) else {
+            return results;
+        };
+
+        for entry in entries {
+            if entry.max_ts < start_nanos || entry.min_ts > end_nanos {
+                continue;
+            }
+            let rows = read_segment_rows(&segment_path(&self.dir, entry.segment_id), entry.segment_id);
+
+            let mut offset = entry.byte_offset as usize;
+            let mut found = 0u32;
+            while found < entry.count && offset + ROW_SIZE <= rows.len() {
+                let row = deserialize_row(&rows[offset..offset + ROW_SIZE]);
+                offset += ROW_SIZE;
+                if row.timeseries_id != timeseries_id {
+                    continue;
+                }
+                found += 1;
+                let row_nanos = row.timestamp.timestamp_nanos_opt().unwrap();
+                if row_nanos >= start_nanos && row_nanos <= end_nanos {
+                    results.push(row);
+                }
+            }
+        }
+
+        results
+    }
+
+    pub(crate) fn update(&mut self, data: TimeseriesData) -> Result<(), String> {
+        let ts = checked_nanos(data.timestamp)?;
+        let Some(entries) = self.index.get(&data.timeseries_id).cloned() else {
+            return Err("Row not found.".into());
+        };
+
+        for entry in entries {
+            if ts < entry.min_ts || ts > entry.max_ts {
+                continue;
+            }
+            let path = segment_path(&self.dir, entry.segment_id);
+            let mut rows = read_segment_rows(&path, entry.segment_id);
+
+            let mut offset = entry.byte_offset as usize;
+            let mut found = 0u32;
+            while found < entry.count && offset + ROW_SIZE <= rows.len() {
+                let row = deserialize_row(&rows[offset..offset + ROW_SIZE]);
+                if row.timeseries_id != data.timeseries_id {
+                    offset += ROW_SIZE;
+                    continue;
+                }
+                found += 1;
+                if row.timestamp == data.timestamp {
+                    serialize_row(&data, &mut rows[offset..offset + ROW_SIZE]);
+                    rewrite_segment_rows(&path, entry.segment_id, &rows).map_err(|e| e.to_string())?;
+                    return Ok(());
+                }
+                offset += ROW_SIZE;
+            }
+        }
+
+        Err("Row not found.".into())
+    }
+
+    /// Reads every row across all segments, oldest first, for a
+    /// point-in-time snapshot. Callers hold the table's lock for the
+    /// duration so this is consistent with any concurrent inserts.
+    pub(crate) fn all_rows(&self) -> Vec<TimeseriesData> {
+        let mut ids = list_segment_ids(&self.dir);
+        ids.sort_unstable();
+
+        let mut rows = Vec::new();
+        for id in ids {
+            let path = segment_path(&self.dir, id);
+            let data = read_segment_rows(&path, id);
+            let row_count = data.len() / ROW_SIZE;
+            for i in 0..row_count {
+                rows.push(deserialize_row(&data[i * ROW_SIZE..(i + 1) * ROW_SIZE]));
+            }
+        }
+        rows
+    }
+
+    /// Discards all segments and starts over with a single empty active
+    /// segment. Used by `Table::restore` to replace live data with a
+    /// snapshot's rows.
+    pub(crate) fn reset(&mut self) -> std::io::Result<()> {
+        for id in list_segment_ids(&self.dir) {
+            fs::remove_file(segment_path(&self.dir, id))?;
+        }
+        self.index.clear();
+        self.active_id = 0;
+        self.active_len = 0;
+        self.active_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(&self.dir, self.active_id))?;
+        Ok(())
+    }
+}
+
+/// Converts a timestamp to epoch-nanoseconds, rejecting ones outside the
+/// representable `i64` range instead of panicking. This is the validation
+/// boundary for anything that ends up on disk -- [`SegmentStore::insert`]
+/// and [`SegmentStore::update`] call it before writing a row, so every
+/// stored timestamp is guaranteed to round-trip through `timestamp_nanos_opt`
+/// and callers further down (e.g. [`SegmentStore::query`] reading rows back)
+/// can keep unwrapping it.
+fn checked_nanos(ts: DateTime<Utc>) -> Result<i64, String> {
+    ts.timestamp_nanos_opt().ok_or_else(|| {
+        "timestamp out of range for nanosecond-precision storage (supported range is roughly \
+         1677-09-21 to 2262-04-11)"
+            .to_string()
+    })
+}
+
+/// Converts a timestamp to epoch-nanoseconds for use as a query bound,
+/// saturating to the nearest representable extreme instead of panicking if
+/// it's out of range. No stored row can be outside `i64`'s range (every
+/// insert goes through [`checked_nanos`]), so a bound beyond it just means
+/// "unbounded" on that side.
+fn saturating_nanos(ts: DateTime<Utc>) -> i64 {
+    ts.timestamp_nanos_opt()
+        .unwrap_or(if ts.year() < 1970 { i64::MIN } else { i64::MAX })
+}
+
+/// The number of live data bytes in a segment file, excluding its trailer
+/// (if it has one). This is agnostic to whether the data itself is raw or
+/// compressed -- the trailer always sits at the very end of the file.
+fn segment_data_len(path: &Path, segment_id: u32) -> u64 {
+    let len = fs::metadata(path)
+        .expect("Unable to stat segment file")
+        .len();
+    match try_read_trailer(path, len, segment_id) {
+        Some(entries) => len - 8 - 4 - (entries.len() * TRAILER_ENTRY_SIZE) as u64,
+        None => len,
+    }
+}
+
+/// Returns every row's fixed-size bytes for a segment, decompressing the
+/// data region first if it was written in the compressed format.
+fn read_segment_rows(path: &Path, segment_id: u32) -> Vec<u8> {
+    let data_len = segment_data_len(path, segment_id) as usize;
+    let mut file = File::open(path).expect("Unable to open segment file");
+    let mut region = vec![0u8; data_len];
+    file.read_exact(&mut region)
+        .expect("Unable to read segment data region");
+
+    if data_len >= COMPRESSED_SEGMENT_HEADER_SIZE && region[..8] == *COMPRESSED_SEGMENT_MAGIC {
+        let codec = CompressionType::from_u8(region[8]);
+        let compressed_len = u64::from_le_bytes(region[9..17].try_into().unwrap()) as usize;
+        compression::decompress(codec, &region[COMPRESSED_SEGMENT_HEADER_SIZE..][..compressed_len])
+    } else {
+        region
+    }
+}
+
+/// Rewrites a segment's data region with `rows`, preserving its trailer
+/// and whichever format (raw or compressed, and if compressed, which
+/// codec) it already had. Used by [`SegmentStore::update`] after an
+/// in-place row edit.
+fn rewrite_segment_rows(path: &Path, segment_id: u32, rows: &[u8]) -> std::io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    let data_len = segment_data_len(path, segment_id);
+
+    let mut old = File::open(path)?;
+    let mut old_region = vec![0u8; data_len as usize];
+    old.read_exact(&mut old_region)?;
+
+    let codec = if data_len as usize >= COMPRESSED_SEGMENT_HEADER_SIZE
+        && old_region[..8] == *COMPRESSED_SEGMENT_MAGIC
+    {
+        CompressionType::from_u8(old_region[8])
+    } else {
+        CompressionType::None
+    };
+
+    let mut trailer = vec![0u8; (len - data_len) as usize];
+    old.seek(SeekFrom::Start(data_len))?;
+    old.read_exact(&mut trailer)?;
+    drop(old);
+
+    write_segment_with_codec(path, codec, rows, &trailer)
+}
+
+/// Writes a segment file from scratch: `rows` encoded under `codec`
+/// followed verbatim by `trailer`. Used both by [`rewrite_segment_rows`]
+/// and by [`SegmentStore::compact`].
+fn write_segment_with_codec(
+    path: &Path,
+    codec: CompressionType,
+    rows: &[u8],
+    trailer: &[u8],
+) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+    match codec {
+        CompressionType::None => file.write_all(rows)?,
+        CompressionType::Lz4 | CompressionType::Gorilla => {
+            let compressed = compression::compress(codec, rows);
+            file.write_all(COMPRESSED_SEGMENT_MAGIC)?;
+            file.write_all(&[codec as u8])?;
+            file.write_all(&(compressed.len() as u64).to_le_bytes())?;
+            file.write_all(&compressed)?;
+        }
+    }
+    file.write_all(trailer)?;
+    file.sync_all()
+}
+
+fn segment_path(dir: &Path, id: u32) -> PathBuf {
+    dir.join(format!("segment-{:06}.seg", id))
+}
+
+fn list_segment_ids(dir: &Path) -> Vec<u32> {
+    let mut ids = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return ids;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if let Some(id_str) = name.strip_prefix("segment-").and_then(|s| s.strip_suffix(".seg")) {
+            if let Ok(id) = id_str.parse::<u32>() {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Reads a closed segment's trailer (index entries written at roll time),
+/// returning `None` if the trailer is missing, truncated, or doesn't end
+/// in the expected magic -- in which case the caller should fall back to
+/// [`scan_segment`].
+fn try_read_trailer(path: &Path, len: u64, segment_id: u32) -> Option<Vec<(Uuid, SegmentIndexEntry)>> {
+    if len < 8 + 4 {
+        return None;
+    }
+    let mut file = File::open(path).ok()?;
+
+    file.seek(SeekFrom::Start(len - 8)).ok()?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic).ok()?;
+    if &magic != TRAILER_MAGIC {
+        return None;
+    }
+
+    file.seek(SeekFrom::Start(len - 8 - 4)).ok()?;
+    let mut count_bytes = [0u8; 4];
+    file.read_exact(&mut count_bytes).ok()?;
+    let count = u32::from_le_bytes(count_bytes) as usize;
+
+    let entries_len = count * TRAILER_ENTRY_SIZE;
+    let trailer_start = len.checked_sub(8 + 4 + entries_len as u64)?;
+
+    file.seek(SeekFrom::Start(trailer_start)).ok()?;
+    let mut entries_bytes = vec![0u8; entries_len];
+    file.read_exact(&mut entries_bytes).ok()?;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let chunk = &entries_bytes[i * TRAILER_ENTRY_SIZE..(i + 1) * TRAILER_ENTRY_SIZE];
+        let uuid = Uuid::from_bytes(chunk[0..16].try_into().unwrap());
+        let min_ts = i64::from_le_bytes(chunk[16..24].try_into().unwrap());
+        let max_ts = i64::from_le_bytes(chunk[24..32].try_into().unwrap());
+        let byte_offset = u64::from_le_bytes(chunk[32..40].try_into().unwrap());
+        let row_count = u32::from_le_bytes(chunk[40..44].try_into().unwrap());
+        entries.push((
+            uuid,
+            SegmentIndexEntry {
+                min_ts,
+                max_ts,
+                segment_id,
+                byte_offset,
+                count: row_count,
+            },
+        ));
+    }
+
+    Some(entries)
+}
+
+/// Sequentially scans a segment (ignoring any trailing trailer bytes,
+/// which aren't a multiple of `ROW_SIZE` and would fail to parse as a
+/// row) to rebuild its index entries -- used for a crashed/never-rolled
+/// segment that has no trailer yet.
+fn scan_segment(path: &Path, segment_id: u32, len: u64) -> Vec<(Uuid, SegmentIndexEntry)> {
+    let mut file = File::open(path).expect("Unable to open segment file for scan");
+    let row_count = (len / ROW_SIZE as u64) as usize;
+
+    let mut by_id: HashMap<Uuid, SegmentIndexEntry> = HashMap::new();
+    let mut buf = vec![0u8; ROW_SIZE];
+    for i in 0..row_count {
+        file.read_exact(&mut buf)
+            .expect("Unable to read row while scanning segment");
+        let row = deserialize_row(&buf);
+        let ts = row.timestamp.timestamp_nanos_opt().unwrap();
+        let offset = i as u64 * ROW_SIZE as u64;
+        by_id
+            .entry(row.timeseries_id)
+            .and_modify(|e| {
+                e.min_ts = e.min_ts.min(ts);
+                e.max_ts = e.max_ts.max(ts);
+                e.count += 1;
+            })
+            .or_insert(SegmentIndexEntry {
+                min_ts: ts,
+                max_ts: ts,
+                segment_id,
+                byte_offset: offset,
+                count: 1,
+            });
+    }
+
+    by_id.into_iter().collect()
+}
+
+fn merge_entries(index: &mut HashMap<Uuid, Vec<SegmentIndexEntry>>, entries: Vec<(Uuid, SegmentIndexEntry)>) {
+    for (uuid, entry) in entries {
+        index.entry(uuid).or_default().push(entry);
+    }
+}